@@ -2,6 +2,15 @@
 // pulls all the prelude into our current scope.
 use anchor_lang::prelude::*;
 
+// anchor_spl is the crate that wraps the actual SPL Token program for us, so we can CPI into
+// mint_to the same way we write our own instructions. Mint/TokenAccount are the account types,
+// Token is the program itself, and AssociatedToken is the program that figures out the standard
+// "this wallet's account for this mint" address for us.
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token::{self, Mint, MintTo, Token, TokenAccount},
+};
+
 // '::' is just rust separator for namespaces, similar to '.' in other languages.
 
 // a program also has a program id also called an Address. We need to set up a program address for our smart contract.
@@ -9,14 +18,17 @@ use anchor_lang::prelude::*;
 // declare_id!("CRGr5Y2bChPmkVShA9E3DrLTTQ1MUvS9TDf6fFNADgjC"); -- initial code
 declare_id!("CRGr5Y2bChPmkVShA9E3DrLTTQ1MUvS9TDf6fFNADgjC"); // automatically added by solana playground - our program's deployed address.
 
-/* 
-The ANCHOR_DISCRIMINATOR_SIZE is something that is written to every account on the blockchain by an Anchor Program. 
-It specifies the type of account it is. 
-It's used by Anchor for some of it's checks. And when we save things to the blockchain, 
+/*
+The ANCHOR_DISCRIMINATOR_SIZE is something that is written to every account on the blockchain by an Anchor Program.
+It specifies the type of account it is.
+It's used by Anchor for some of it's checks. And when we save things to the blockchain,
 we'll need 8 bytes plus the size of whatever we're saving.
  */
 pub const ANCHOR_DISCRIMINATOR_SIZE: usize = 8; // 8 - size in bytes every anchor account needs minimum
 
+// just 1 whole "participation" token per user, since the mint is created with 0 decimals.
+pub const PARTICIPATION_TOKEN_AMOUNT: u64 = 1;
+
 // The great thing about Anchor is that we can take a regular Rust program and turn it into a Anchor program using a Macro.
 
 #[program] // Solana Program Macro. And inside this module each function would become an Anchor instruction handler.
@@ -43,19 +55,106 @@ pub mod favorites {
             hobbies
         );
 
+        // the #[max_len(...)] stuff on the Favorites struct only reserves space for Anchor, it
+        // doesn't actually stop someone from handing us a color or hobbies list that's too big.
+        // if we didn't check this ourselves, set_inner below would just blow up with some
+        // confusing serialization panic instead of a nice error message. so check it first.
+        validate_favorites_inputs(&color, &hobbies)?;
+
         // write the information into favorite account provided.
 
         context.accounts.favorites.set_inner(Favorites {
             // set_inner - would write the information into the account.
             number,
+            color: color.clone(),
+            hobbies: hobbies.clone(),
+            owner: user_public_key, // who owns this account, straight from the account data this time instead of only the PDA seeds.
+            updated_at: Clock::get()?.unix_timestamp, // Clock sysvar, so we know (and clients can show) when this was last touched.
+        });
+
+        // emit! just writes this into the transaction logs. Lets front-ends/indexers listen for
+        // favorites changing instead of having to keep re-fetching the account every time.
+        emit!(FavoritesUpdated {
+            user: user_public_key,
+            number,
             color,
             hobbies,
         });
 
+        // give the user a little "participation" token the very first time they ever set their
+        // favorites. participation_record is its own separate PDA (see below) so this can't be
+        // farmed by closing the Favorites account and setting it again.
+        if !context.accounts.participation_record.minted {
+            msg!("First time setting favorites - minting a participation token");
+
+            // mint_authority doesn't have a private key, it's a PDA, so we "sign" for it using its
+            // seeds instead of an actual signature.
+            let signer_seeds: &[&[&[u8]]] = &[&[b"mint_authority", &[context.bumps.mint_authority]]];
+
+            token::mint_to(
+                CpiContext::new_with_signer(
+                    context.accounts.token_program.to_account_info(),
+                    MintTo {
+                        mint: context.accounts.mint.to_account_info(),
+                        to: context.accounts.user_token_account.to_account_info(),
+                        authority: context.accounts.mint_authority.to_account_info(),
+                    },
+                    signer_seeds,
+                ),
+                PARTICIPATION_TOKEN_AMOUNT,
+            )?;
+
+            context.accounts.participation_record.minted = true;
+        }
+
         // return with ok
 
         Ok(()) // no need for the ';' and Rust would return the actual ok response. They write info to the blockchain rather than returning it.
     }
+
+    // set_favorites uses init_if_needed above, so the first call creates the account and every
+    // later call just overwrites whatever's there already - creating and editing are kind of
+    // mashed together into the one instruction. Most of the Anchor tutorials keep those separate,
+    // so here's update_favorites for editing an account that's already been created, without
+    // going anywhere near the init/payer stuff again.
+    pub fn update_favorites(
+        context: Context<UpdateFavorites>,
+        number: u64,
+        color: String,
+        hobbies: Vec<String>,
+    ) -> Result<()> {
+        let user_public_key: Pubkey = context.accounts.user.key();
+        msg!("Updating favorites for {}", user_public_key);
+
+        // same checks as set_favorites - this is the main way people will be editing their
+        // favorites now, so it needs the same guard against oversized input.
+        validate_favorites_inputs(&color, &hobbies)?;
+
+        context.accounts.favorites.set_inner(Favorites {
+            number,
+            color: color.clone(),
+            hobbies: hobbies.clone(),
+            owner: user_public_key,
+            updated_at: Clock::get()?.unix_timestamp,
+        });
+
+        emit!(FavoritesUpdated {
+            user: user_public_key,
+            number,
+            color,
+            hobbies,
+        });
+
+        Ok(())
+    }
+
+    // Accounts cost rent for as long as they stick around on-chain, so it's only fair we give
+    // people a way to delete their own Favorites account and get that rent back.
+    pub fn close_favorites(_context: Context<CloseFavorites>) -> Result<()> {
+        msg!("Closing favorites account");
+
+        Ok(()) // the `close = user` constraint below does the actual work - wipes the data and sends the lamports back.
+    }
 }
 
 // struct for writing what we want to write to the blockchain.
@@ -73,9 +172,64 @@ pub struct Favorites {
 
     #[max_len(5, 50)] //vector of size 5 and each of 50 bytes.
     pub hobbies: Vec<String>,
+
+    pub owner: Pubkey, // who created/last touched this account - can check this straight from the account data now, don't have to re-derive the PDA.
+
+    pub updated_at: i64, // unix timestamp from the Clock sysvar, last time set_favorites/update_favorites ran.
+}
+
+// tracks whether someone's already gotten their one-time participation token. this is its own
+// account on purpose - Favorites can get closed and recreated via close_favorites + set_favorites,
+// but there's no close instruction for this one, so nobody can farm the token that way.
+#[account]
+#[derive(InitSpace)]
+pub struct ParticipationRecord {
+    pub minted: bool,
+}
+
+// #[event] - Anchor will serialize this into the tx logs whenever we emit!(...) it. means
+// front-ends/indexers can just listen for it instead of re-fetching the Favorites account
+// every time.
+#[event]
+pub struct FavoritesUpdated {
+    pub user: Pubkey,
+    pub number: u64,
+    pub color: String,
+    pub hobbies: Vec<String>,
+}
+
+// #[error_code] turns a plain enum into our own program error codes, each variant gets a number
+// and the #[msg(...)] is the text people see back when a require! using that variant fails.
+#[error_code]
+pub enum FavoritesError {
+    #[msg("Favorite color cannot be empty")]
+    ColorEmpty,
+
+    #[msg("Favorite color must be 50 bytes or fewer")]
+    ColorTooLong,
+
+    #[msg("You can only list up to 5 hobbies")]
+    TooManyHobbies,
+
+    #[msg("Each hobby must be 50 bytes or fewer")]
+    HobbyTooLong,
 }
 
-/* when people call our set_favorites function, they need to provide a list of accounts that they need to change on the blockchain. 
+// set_favorites and update_favorites both call favorites.set_inner with the same kind of data,
+// so may as well keep the input checks in one place instead of copy-pasting them twice.
+fn validate_favorites_inputs(color: &str, hobbies: &[String]) -> Result<()> {
+    require!(!color.is_empty(), FavoritesError::ColorEmpty);
+    require!(color.len() <= 50, FavoritesError::ColorTooLong);
+    require!(hobbies.len() <= 5, FavoritesError::TooManyHobbies);
+    require!(
+        hobbies.iter().all(|hobby| hobby.len() <= 50),
+        FavoritesError::HobbyTooLong
+    );
+
+    Ok(())
+}
+
+/* when people call our set_favorites function, they need to provide a list of accounts that they need to change on the blockchain.
 One of the things that makes Solana Blockchain great is that if there's a bunch of people over at A who are running a transaction involing their accounts
 and there's a bunch of people at B running a different transaction involing their accounts, the transactions need not block eachother, there's no overlap evolved.
 Solana can process them at the same time without waiting for the other one to be finished.
@@ -98,26 +252,111 @@ pub struct SetFavorites<'info> {
     #[account(
         init_if_needed, // init_if_needed - make the account if it doesn't already exist.
         payer = user, // payer - who pays to create the account, 'user' - person who Signed the transaction.
-        space = ANCHOR_DISCRIMINATOR_SIZE + Favorites::INIT_SPACE, // space - how much space the account needs, 
+        space = ANCHOR_DISCRIMINATOR_SIZE + Favorites::INIT_SPACE, // space - how much space the account needs,
                                                                   //when declaring the struct we used the derive(InitSpace) which would help us to calc the size for the Favorites account.
         seeds = [b"favorites", user.key().as_ref()], // seeds - we will need to have seeds which are used to give this account an address on the blockchain, this is a PDA. Unlike a regular user account this isn't a public key.
                                                      // The address for this is actually made based on some seeds that we provide.
                                                      // Here, we're using the text favorites as bytes and user's own key. This means that if I'm storing my favorites: I'll store that under the address made from 'favorites' as bytes,
-                                                     // and user's own public key, 
-        
+                                                     // and user's own public key,
+
         bump // bump - used to calculate those seeds.
     )]
     pub favorites: Account<'info, Favorites>, // an account of the Favorites struct we made earlier.
 
+    // a PDA that exists only to be the mint's authority - no data in it, we just need its address
+    // and bump so we can "sign" the mint_to CPI with the seeds instead of a real keypair.
+    // (declared before mint because mint's init constraint below refers to it.)
+    /// CHECK: never deserialized as anything, it's only used to sign the mint_to CPI.
+    #[account(seeds = [b"mint_authority"], bump)]
+    pub mint_authority: UncheckedAccount<'info>,
+
+    // the mint for our "participation" token. Pinned down to one PDA via seeds/bump so nobody
+    // can swap in some random mint of their own and trick us into minting it for them - its mint
+    // authority has to be mint_authority above. init_if_needed creates it on the very first call
+    // (0 decimals, mint_authority as authority) and checks both on every call after that.
+    #[account(
+        init_if_needed,
+        payer = user,
+        seeds = [b"participation_mint"],
+        bump,
+        mint::decimals = 0,
+        mint::authority = mint_authority,
+    )]
+    pub mint: Account<'info, Mint>,
+
+    // the user's associated token account (ATA) for our mint - basically the standard, always-
+    // the-same address for "this wallet's account for this particular mint". init_if_needed
+    // because a first-time user won't have one, and they pay for it just like the Favorites PDA.
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = mint,
+        associated_token::authority = user,
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+
+    // keeps track of whether this user already got their participation token (see the comment on
+    // ParticipationRecord above for why it can't just live on the Favorites account instead).
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = ANCHOR_DISCRIMINATOR_SIZE + ParticipationRecord::INIT_SPACE,
+        seeds = [b"participation", user.key().as_ref()],
+        bump,
+    )]
+    pub participation_record: Account<'info, ParticipationRecord>,
+
+    // the SPL Token program itself, need this to CPI into mint_to.
+    pub token_program: Program<'info, Token>,
+
+    // needed because user_token_account gets created via init_if_needed + associated_token::*.
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
     // last account we'll need people to specify is just the system program. Used for so many things, It's not the system program, it's the token program.
     // The program will last the lifetime of the infor and it is a program of type system.
     pub system_program: Program<'info, System>,
+
+    pub rent: Sysvar<'info, Rent>, // the init_if_needed associated token account up above needs this.
 }
 
 /*
- the program ensures that the person is already signing the program has to be writing to their own favorites account. 
- Logic - in the 'seed' we're use the user's key where user is the one who signed the transaction. 
+ the program ensures that the person is already signing the program has to be writing to their own favorites account.
+ Logic - in the 'seed' we're use the user's key where user is the one who signed the transaction.
  Good example of the things that Anchor provides like smart safe defaults.
 
  Controls over what accounts people are able to write to are handled by the programmer.
  */
+
+// accounts for update_favorites - unlike SetFavorites there's no init_if_needed and no payer,
+// the account has to already exist, we're just loading it up so the owner can overwrite it.
+#[derive(Accounts)]
+pub struct UpdateFavorites<'info> {
+    pub user: Signer<'info>, // still needs to sign, otherwise anyone could call update on anyone else's PDA.
+
+    #[account(
+        mut, // mutable since we're writing new data, but no init/init_if_needed here - it has to already exist.
+        seeds = [b"favorites", user.key().as_ref()], // same seeds as SetFavorites - how we find the signer's own PDA.
+        bump, // same bump trick, used to re-derive the PDA's address.
+    )]
+    pub favorites: Account<'info, Favorites>,
+    // don't need a has_one check here - the PDA's address is already derived from user.key(), so
+    // if someone signs as a different person, the seeds just won't match their own Favorites
+    // account and Anchor's PDA re-derivation rejects it for us. Same trick SetFavorites relies on.
+}
+
+// accounts for close_favorites.
+#[derive(Accounts)]
+pub struct CloseFavorites<'info> {
+    #[account(mut)] // mutable - the user's lamport balance goes up when the account gets closed.
+    pub user: Signer<'info>, // has to sign, so only the owner can close their own Favorites PDA.
+
+    #[account(
+        mut,
+        seeds = [b"favorites", user.key().as_ref()], // same PDA derivation as everywhere else, ties this account to the signer.
+        bump,
+        close = user, // close - Anchor's built-in account-closing constraint: wipes the data and
+                      // sends the reclaimed rent lamports to `user`, the account named here.
+    )]
+    pub favorites: Account<'info, Favorites>,
+    // same deal as UpdateFavorites, no has_one needed, the seeds already tie this to the signer.
+}